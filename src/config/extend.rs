@@ -1,14 +1,22 @@
 use crate::{exit, util};
-use globset::Glob;
+use globset::{Glob, GlobBuilder};
 use hyper::header::{HeaderName, HeaderValue};
 use hyper::{Method, Uri};
-use regex::Regex;
+use regex::{Regex, RegexBuilder};
 use std::net::{IpAddr, SocketAddr};
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::time::Duration;
 use util::{try_parse_duration, try_parse_size, try_to_socket_addr};
 
+// Options controlling glob compilation, mirroring `globset::GlobBuilder`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GlobOptions {
+    pub literal_separator: bool,
+    pub case_insensitive: bool,
+    pub backslash_escape: bool,
+}
+
 // Get the extension of the path
 pub trait PathExtension {
     fn get_extension(&self) -> Option<&str>;
@@ -43,10 +51,12 @@ pub trait Force {
     fn to_duration(&self) -> Duration;
     fn to_size(&self) -> usize;
     fn to_glob(&self) -> Glob;
+    fn to_glob_with(&self, opts: GlobOptions) -> Glob;
     fn to_header_name(&self) -> HeaderName;
     fn to_header_value(&self) -> HeaderValue;
     fn to_method(&self) -> Method;
     fn to_regex(&self) -> Regex;
+    fn to_regex_ci(&self) -> Regex;
     fn to_socket_addr(&self) -> SocketAddr;
     fn to_ip_addr(&self) -> IpAddr;
     fn to_strftime(&self);
@@ -66,7 +76,15 @@ impl Force for &str {
     }
 
     fn to_glob(&self) -> Glob {
-        Glob::new(self)
+        self.to_glob_with(GlobOptions::default())
+    }
+
+    fn to_glob_with(&self, opts: GlobOptions) -> Glob {
+        GlobBuilder::new(self)
+            .literal_separator(opts.literal_separator)
+            .case_insensitive(opts.case_insensitive)
+            .backslash_escape(opts.backslash_escape)
+            .build()
             .unwrap_or_else(|err| exit!("Cannot parse `{}` to glob matcher\n{}", self, err))
     }
 
@@ -90,6 +108,19 @@ impl Force for &str {
             .unwrap_or_else(|err| exit!("Cannot parse `{}` to regular expression\n{}", self, err))
     }
 
+    fn to_regex_ci(&self) -> Regex {
+        RegexBuilder::new(self)
+            .case_insensitive(true)
+            .build()
+            .unwrap_or_else(|err| {
+                exit!(
+                    "Cannot parse `{}` to case-insensitive regular expression\n{}",
+                    self,
+                    err
+                )
+            })
+    }
+
     fn to_socket_addr(&self) -> SocketAddr {
         try_to_socket_addr(self).unwrap_or_else(|_| exit!("Cannot parse `{}` to SocketAddr", self))
     }
@@ -1,22 +1,48 @@
-use crate::config::Force;
+use crate::config::{Force, GlobOptions};
+use crate::exit;
 use crate::matcher::{replace_match_keyword, END_WORD, REGEX_WORD, START_WORD};
 use globset::GlobMatcher;
-use regex::Regex;
+use regex::{Regex, RegexSet};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
 
 // Match location
 #[derive(Debug, Clone)]
 pub struct LocationMatcher(MatchMode);
 
+const NOT_WORD: char = '!';
+const CI_REGEX_WORD: &str = "~*";
+
 #[derive(Debug, Clone)]
 enum MatchMode {
     Glob(GlobMatcher),
     Regex(Regex),
     Start(String),
     End(String),
+    Param(Regex, Vec<String>),
+    Not(Box<MatchMode>),
 }
 
 impl LocationMatcher {
     pub fn new(location: &str) -> Self {
+        Self::new_with(location, GlobOptions::default())
+    }
+
+    // Like `new`, but lets a location block opt into strict glob semantics,
+    // e.g. a literal `/` separator or case-insensitive matching
+    pub fn new_with(location: &str, glob_opts: GlobOptions) -> Self {
+        // Negation, e.g. `!/test/*` or `!~/test/.*`
+        if let Some(raw) = location.strip_prefix(NOT_WORD) {
+            let LocationMatcher(inner) = Self::new_with(raw, glob_opts);
+            return LocationMatcher(MatchMode::Not(Box::new(inner)));
+        }
+
+        // Case-insensitive regex
+        if let Some(raw) = location.strip_prefix(CI_REGEX_WORD) {
+            let reg = raw.to_regex_ci();
+            return LocationMatcher(MatchMode::Regex(reg));
+        }
+
         // Regex
         if let Some(raw) = replace_match_keyword(location, REGEX_WORD) {
             let reg = raw.as_str().to_regex();
@@ -33,21 +59,306 @@ impl LocationMatcher {
             return LocationMatcher(MatchMode::End(raw));
         }
 
+        // Param, e.g. `/user/:id/post/:slug` or `/files/*tail`
+        if has_param_segment(location) {
+            let (reg, names) = build_param_regex(location);
+            return LocationMatcher(MatchMode::Param(reg, names));
+        }
+
         // Glob
-        let glob = location.to_glob().compile_matcher();
+        let glob = location.to_glob_with(glob_opts).compile_matcher();
         LocationMatcher(MatchMode::Glob(glob))
     }
 
     pub fn is_match(&self, path: &str) -> bool {
-        match &self.0 {
-            MatchMode::Glob(glob) => glob.is_match(path),
-            MatchMode::Regex(reg) => reg.is_match(path),
-            MatchMode::Start(s) => path.starts_with(s),
-            MatchMode::End(s) => path.ends_with(s),
+        let path = normalize_path(path);
+        // A path that failed to normalize (NUL byte, traversal past root)
+        // must not silently fall through to a broader rule
+        if path.contains('\0') {
+            return false;
         }
+
+        mode_is_match(&self.0, path.as_ref())
+    }
+
+    // Capture named route segments, e.g. `:id` or `*tail`, percent-decoded.
+    // Matches against the same normalized path `is_match` uses, so the two
+    // methods never disagree about what a raw request path contains.
+    pub fn captures(&self, path: &str) -> Option<BTreeMap<String, String>> {
+        let (reg, names) = match &self.0 {
+            MatchMode::Param(reg, names) => (reg, names),
+            _ => return None,
+        };
+
+        let path = normalize_path(path);
+        if path.contains('\0') {
+            return None;
+        }
+
+        let caps = reg.captures(path.as_ref())?;
+        let mut params = BTreeMap::new();
+        for name in names {
+            if let Some(value) = caps.name(name) {
+                params.insert(name.clone(), value.as_str().to_string());
+            }
+        }
+        Some(params)
     }
 }
 
+fn mode_is_match(mode: &MatchMode, path: &str) -> bool {
+    match mode {
+        MatchMode::Glob(glob) => glob.is_match(path),
+        MatchMode::Regex(reg) => reg.is_match(path),
+        MatchMode::Start(s) => path.starts_with(s),
+        MatchMode::End(s) => path.ends_with(s),
+        MatchMode::Param(reg, _) => reg.is_match(path),
+        MatchMode::Not(inner) => !mode_is_match(inner, path),
+    }
+}
+
+// A dynamic segment's name must look like an identifier, so a bare glob
+// wildcard (`*`, `*.png`) is never mistaken for a route param
+fn is_param_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// Whether the location contains a `:name` or `*name` dynamic segment
+fn has_param_segment(location: &str) -> bool {
+    location.split('/').any(|segment| {
+        segment
+            .strip_prefix(':')
+            .or_else(|| segment.strip_prefix('*'))
+            .is_some_and(is_param_name)
+    })
+}
+
+// Translate a route pattern like `/user/:id/*rest` into an anchored regex,
+// returning it alongside the ordered list of captured parameter names
+fn build_param_regex(location: &str) -> (Regex, Vec<String>) {
+    let mut names = Vec::new();
+    let mut parts = Vec::new();
+
+    for segment in location.split('/') {
+        if let Some(name) = segment.strip_prefix(':').filter(|name| is_param_name(name)) {
+            names.push(name.to_string());
+            parts.push(format!("(?P<{}>[^/]+)", name));
+        } else if let Some(name) = segment.strip_prefix('*').filter(|name| is_param_name(name)) {
+            names.push(name.to_string());
+            parts.push(format!("(?P<{}>.*)", name));
+        } else {
+            parts.push(regex::escape(segment));
+        }
+    }
+
+    let pattern = format!("^{}$", parts.join("/"));
+    (pattern.as_str().to_regex(), names)
+}
+
+// Compile every location rule into a single `RegexSet` for one-pass routing,
+// falling back to individual `LocationMatcher`s for any rule that can't be
+// expressed as a plain regex (e.g. a `Param` route, a negated rule, or a
+// glob whose byte-vs-Unicode-mode semantics wouldn't survive translation)
+pub struct LocationMatcherSet {
+    set: RegexSet,
+    set_rules: Vec<usize>,
+    fallback: Vec<(usize, LocationMatcher)>,
+}
+
+impl LocationMatcherSet {
+    pub fn new(locations: &[String]) -> Self {
+        Self::new_with(locations, GlobOptions::default())
+    }
+
+    // Like `new`, but threads glob options through to every rule, whether it
+    // lands in the `RegexSet` or in the per-matcher fallback
+    pub fn new_with(locations: &[String], glob_opts: GlobOptions) -> Self {
+        let mut patterns = Vec::new();
+        let mut set_rules = Vec::new();
+        let mut fallback = Vec::new();
+
+        for (index, location) in locations.iter().enumerate() {
+            match rule_to_regex_str(location, glob_opts) {
+                Some(pattern) => {
+                    patterns.push(pattern);
+                    set_rules.push(index);
+                }
+                None => fallback.push((index, LocationMatcher::new_with(location, glob_opts))),
+            }
+        }
+
+        let set = RegexSet::new(&patterns).unwrap_or_else(|err| {
+            exit!("Cannot compile location rules to a regex set\n{}", err)
+        });
+
+        LocationMatcherSet {
+            set,
+            set_rules,
+            fallback,
+        }
+    }
+
+    // All rule indices (in the original `locations` order) that match `path`
+    pub fn matches(&self, path: &str) -> Vec<usize> {
+        let path = normalize_path(path);
+        if path.contains('\0') {
+            return Vec::new();
+        }
+        let path = path.as_ref();
+
+        let mut matched: Vec<usize> = self
+            .set
+            .matches(path)
+            .into_iter()
+            .map(|i| self.set_rules[i])
+            .collect();
+
+        for (index, matcher) in &self.fallback {
+            if mode_is_match(&matcher.0, path) {
+                matched.push(*index);
+            }
+        }
+
+        matched.sort_unstable();
+        matched
+    }
+
+    pub fn is_match(&self, path: &str) -> bool {
+        let path = normalize_path(path);
+        if path.contains('\0') {
+            return false;
+        }
+        let path = path.as_ref();
+
+        self.set.is_match(path) || self.fallback.iter().any(|(_, matcher)| mode_is_match(&matcher.0, path))
+    }
+}
+
+// Convert a single location rule into a regex string that matches the same
+// paths as its `MatchMode` (a `~`/`~*` rule is used as written, unanchored;
+// `^` and `$` rules are anchored at the start and end), or `None` if
+// the rule can't be expressed as a plain regex (e.g. a `Param` route or a
+// negated rule, which falls back to an individual `LocationMatcher`)
+fn rule_to_regex_str(location: &str, glob_opts: GlobOptions) -> Option<String> {
+    if location.starts_with(NOT_WORD) {
+        return None;
+    }
+
+    if let Some(raw) = location.strip_prefix(CI_REGEX_WORD) {
+        return Some(format!("(?i){}", raw));
+    }
+
+    if let Some(raw) = replace_match_keyword(location, REGEX_WORD) {
+        return Some(raw);
+    }
+
+    if let Some(raw) = replace_match_keyword(location, START_WORD) {
+        return Some(format!("^{}", regex::escape(&raw)));
+    }
+
+    if let Some(raw) = replace_match_keyword(location, END_WORD) {
+        return Some(format!("{}$", regex::escape(&raw)));
+    }
+
+    if has_param_segment(location) {
+        return None;
+    }
+
+    // `Glob::regex` disables Unicode mode (`(?-u)`) for speed, which a plain
+    // `&str` RegexSet rejects as possibly matching invalid UTF-8. Re-enabling
+    // it is only safe for plain `*`/`**`/literal segments: a non-ASCII glob
+    // pattern, a single-char wildcard (`?`), or a bracket class can match
+    // differently once re-parsed in Unicode mode, so send those to the
+    // per-matcher fallback instead, where `GlobMatcher`'s own byte semantics
+    // are preserved.
+    if !location.is_ascii() || location.contains('?') || location.contains('[') {
+        return None;
+    }
+
+    let glob_regex = location.to_glob_with(glob_opts).regex().to_string();
+    Some(glob_regex.trim_start_matches("(?-u)").to_string())
+}
+
+// Percent-decode, collapse repeated `/` and resolve `.`/`..` dot-segments in
+// a request path before it reaches a matcher. A path that NUL bytes or whose
+// `..` climbs past the root normalizes to `"\0"`, which no rule should match.
+pub fn normalize_path(path: &str) -> Cow<'_, str> {
+    let decoded = percent_decode(path);
+
+    if decoded.contains('\0') {
+        return Cow::Borrowed("\0");
+    }
+
+    // A genuine trailing separator (e.g. the nginx `location /dir/ {}`
+    // idiom) must survive normalization instead of being folded away like
+    // an empty segment
+    let had_trailing_slash = decoded.len() > 1 && decoded.ends_with('/');
+
+    let mut segments: Vec<&str> = Vec::new();
+    let mut escaped_root = false;
+    for segment in decoded.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                if segments.pop().is_none() {
+                    escaped_root = true;
+                }
+            }
+            segment => segments.push(segment),
+        }
+    }
+
+    if escaped_root {
+        return Cow::Borrowed("\0");
+    }
+
+    let mut normalized = format!("/{}", segments.join("/"));
+    if had_trailing_slash && !normalized.ends_with('/') {
+        normalized.push('/');
+    }
+    Cow::Owned(normalized)
+}
+
+// A single ASCII hex digit's value, or `None` if `b` isn't one
+fn hex_digit(b: u8) -> Option<u8> {
+    match b {
+        b'0'..=b'9' => Some(b - b'0'),
+        b'a'..=b'f' => Some(b - b'a' + 10),
+        b'A'..=b'F' => Some(b - b'A' + 10),
+        _ => None,
+    }
+}
+
+// The byte a `%XX` escape at `bytes[i]` decodes to, or `None` if there's no
+// valid escape there (not enough bytes left, or non-hex digits follow)
+fn decoded_escape(bytes: &[u8], i: usize) -> Option<u8> {
+    if bytes[i] != b'%' || i + 2 >= bytes.len() {
+        return None;
+    }
+    let hi = hex_digit(bytes[i + 1])?;
+    let lo = hex_digit(bytes[i + 2])?;
+    Some(hi * 16 + lo)
+}
+
+// Percent-decode a whole request path. Works over raw bytes throughout, so
+// a `%` next to a multi-byte UTF-8 character can never land on a non-char
+// boundary the way `&str` range indexing would.
+fn percent_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if let Some(byte) = decoded_escape(bytes, i) {
+            out.push(byte);
+            i += 3;
+            continue;
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -82,4 +393,184 @@ mod test {
         assert!(matcher.is_match("/test/a"));
         assert!(matcher.is_match("/test/a/b"));
     }
+
+    #[test]
+    fn param() {
+        let matcher = LocationMatcher::new("/user/:id/post/:slug");
+        assert!(matcher.is_match("/user/42/post/hello-world"));
+        assert!(!matcher.is_match("/user/42"));
+
+        let captures = matcher.captures("/user/42/post/hello%20world").unwrap();
+        assert_eq!(captures.get("id").unwrap(), "42");
+        assert_eq!(captures.get("slug").unwrap(), "hello world");
+    }
+
+    #[test]
+    fn param_tail() {
+        let matcher = LocationMatcher::new("/files/*tail");
+        assert!(matcher.is_match("/files/a/b/c.png"));
+
+        let captures = matcher.captures("/files/a/b/c.png").unwrap();
+        assert_eq!(captures.get("tail").unwrap(), "a/b/c.png");
+    }
+
+    #[test]
+    fn captures_and_is_match_agree_on_traversal() {
+        let matcher = LocationMatcher::new("/api/:ver/*rest");
+        let path = "/api/v1/%2e%2e/v1/secret";
+
+        // `is_match` normalizes the `..` away, so `captures` must reflect
+        // that same resolved path rather than smuggling the raw `..` into a
+        // value callers forward to a proxied backend
+        assert!(matcher.is_match(path));
+        let captures = matcher.captures(path).unwrap();
+        assert_eq!(captures.get("ver").unwrap(), "v1");
+        assert_eq!(captures.get("rest").unwrap(), "secret");
+    }
+
+    #[test]
+    fn captures_and_is_match_agree_on_encoded_separator() {
+        let matcher = LocationMatcher::new("/user/:id/post/:slug");
+        let path = "/user/42%2Fextra/post/x";
+
+        // A `%2F` decodes to an extra `/`, producing a path with one segment
+        // too many — `captures` must not match where `is_match` doesn't
+        assert!(!matcher.is_match(path));
+        assert!(matcher.captures(path).is_none());
+    }
+
+    #[test]
+    fn glob_literal_separator() {
+        let opts = GlobOptions {
+            literal_separator: true,
+            ..GlobOptions::default()
+        };
+        let matcher = LocationMatcher::new_with("/test/*", opts);
+        assert!(matcher.is_match("/test/a"));
+        assert!(!matcher.is_match("/test/a/b"));
+    }
+
+    #[test]
+    fn glob_case_insensitive() {
+        let opts = GlobOptions {
+            case_insensitive: true,
+            ..GlobOptions::default()
+        };
+        let matcher = LocationMatcher::new_with("/TEST/*", opts);
+        assert!(matcher.is_match("/test/a"));
+    }
+
+    #[test]
+    fn percent_decode_multibyte_boundary() {
+        // A stray `%` next to a multi-byte UTF-8 character must decode
+        // cleanly instead of panicking on a non-char-boundary index
+        assert_eq!(percent_decode("/price/%€nd"), "/price/%€nd");
+    }
+
+    #[test]
+    fn normalize() {
+        assert_eq!(normalize_path("/a/./b//c"), "/a/b/c");
+        assert_eq!(normalize_path("/a/b/../c"), "/a/c");
+        assert_eq!(normalize_path("/%2e%2e/etc/passwd"), "\0");
+        assert_eq!(normalize_path("/a/../../etc"), "\0");
+    }
+
+    #[test]
+    fn normalize_preserves_trailing_slash() {
+        assert_eq!(normalize_path("/test/"), "/test/");
+        assert_eq!(normalize_path("/test//"), "/test/");
+        assert_eq!(normalize_path("/test"), "/test");
+        assert_eq!(normalize_path("/"), "/");
+    }
+
+    #[test]
+    fn directory_root_still_matches_its_own_trailing_slash_rule() {
+        let matcher = LocationMatcher::new("^/test/");
+        assert!(matcher.is_match("/test/"));
+    }
+
+    #[test]
+    fn traversal_does_not_match() {
+        let matcher = LocationMatcher::new("^/safe/");
+        assert!(!matcher.is_match("/safe/../../etc/passwd"));
+    }
+
+    #[test]
+    fn is_match_decodes_multibyte_request_path_without_panicking() {
+        let matcher = LocationMatcher::new("^/price/");
+        assert!(matcher.is_match("/price/%€nd"));
+    }
+
+    #[test]
+    fn not() {
+        let matcher = LocationMatcher::new("!/test/*");
+        assert!(!matcher.is_match("/test/a"));
+        assert!(matcher.is_match("/other"));
+    }
+
+    #[test]
+    fn not_regex() {
+        let matcher = LocationMatcher::new(r"!~/test/.*");
+        assert!(!matcher.is_match("/test/a"));
+        assert!(matcher.is_match("/other"));
+    }
+
+    #[test]
+    fn regex_case_insensitive() {
+        let matcher = LocationMatcher::new(r"~*/TEST/.*");
+        assert!(matcher.is_match("/test/a"));
+    }
+
+    #[test]
+    fn matcher_set() {
+        let locations: Vec<String> = vec![
+            "^/test/".to_string(),
+            "$.png".to_string(),
+            "/test/*".to_string(),
+            "/user/:id".to_string(),
+        ];
+        let set = LocationMatcherSet::new(&locations);
+
+        assert!(set.is_match("/test/a.png"));
+        assert_eq!(set.matches("/test/a.png"), vec![0, 1, 2]);
+        assert_eq!(set.matches("/user/42"), vec![3]);
+        assert!(set.matches("/other").is_empty());
+    }
+
+    #[test]
+    fn matcher_set_normalizes_path_through_the_regex_set() {
+        let locations: Vec<String> = vec!["^/admin/".to_string()];
+        let set = LocationMatcherSet::new(&locations);
+
+        // A traversal/NUL-carrying path must be rejected the same way it
+        // would be by a standalone `LocationMatcher`, not just by fallback
+        // rules
+        assert!(!set.is_match("/admin/%2e%2e%2fsecret"));
+        assert!(set.matches("/admin/%2e%2e%2fsecret").is_empty());
+        assert!(set.is_match("/admin/panel"));
+    }
+
+    #[test]
+    fn matcher_set_honors_unicode_glob_semantics() {
+        let locations: Vec<String> = vec!["/tést/*".to_string()];
+        let set = LocationMatcherSet::new(&locations);
+
+        // The non-ASCII glob must fall back to a standalone `LocationMatcher`
+        // so it keeps `GlobMatcher`'s byte-based semantics rather than being
+        // silently re-parsed in Unicode mode by the combined `RegexSet`
+        assert!(set.is_match("/tést/x"));
+    }
+
+    #[test]
+    fn matcher_set_honors_glob_options() {
+        let opts = GlobOptions {
+            literal_separator: true,
+            ..GlobOptions::default()
+        };
+        let locations: Vec<String> = vec!["/test/*".to_string()];
+        let set = LocationMatcherSet::new_with(&locations, opts);
+
+        assert!(set.is_match("/test/a"));
+        assert!(!set.is_match("/test/a/b"));
+    }
 }